@@ -0,0 +1,132 @@
+use serde_derive::Serialize;
+
+/// A severity bucket that is easier for an end user to act on than a raw µg/m³ figure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Good,
+    Moderate,
+    Unhealthy,
+    VeryUnhealthy,
+    Hazardous,
+}
+
+impl Severity {
+    /// Maps a genuine airkorea grade to its severity bucket. `Grade::Strange` marks a
+    /// sensor anomaly or a reading still under inspection, not the worst real grade, so
+    /// it is treated as unknown (`None`) the same way a missing grade is, rather than
+    /// forcing the station's overall status to `Hazardous`.
+    fn from_grade(grade: airkorea::Grade) -> Option<Self> {
+        match grade {
+            airkorea::Grade::Good => Some(Severity::Good),
+            airkorea::Grade::Normal => Some(Severity::Moderate),
+            airkorea::Grade::Bad => Some(Severity::Unhealthy),
+            airkorea::Grade::VeryBad => Some(Severity::VeryUnhealthy),
+            airkorea::Grade::Strange => None,
+        }
+    }
+
+    fn advisory(self) -> &'static str {
+        match self {
+            Severity::Good => "air quality is good, enjoy outdoor activity as usual",
+            Severity::Moderate => "air quality is acceptable, sensitive groups should take it easy outdoors",
+            Severity::Unhealthy => "limit prolonged outdoor activity, sensitive groups should stay indoors",
+            Severity::VeryUnhealthy => "avoid outdoor activity and wear a mask if you must go out",
+            Severity::Hazardous => "stay indoors and keep windows closed, conditions are hazardous",
+        }
+    }
+}
+
+/// The interpreted severity of a single pollutant reading.
+#[derive(Debug, Clone, Serialize)]
+pub struct PollutantHealth {
+    pub name: String,
+    pub severity: Severity,
+    pub advisory: String,
+}
+
+/// A health advisory summary derived from an `airkorea::AirStatus`: a severity bucket
+/// and actionable recommendation per pollutant, plus the station's worst-case overall
+/// status.
+#[derive(Debug, Clone, Serialize)]
+pub struct AirHealth {
+    pub overall: Severity,
+    pub advisory: String,
+    pub pollutants: Vec<PollutantHealth>,
+}
+
+/// Computes the station-wide severity as the worst of its per-pollutant readings.
+/// `airkorea::Grade` has no grade above `VeryBad`, so `Severity::Hazardous` is currently
+/// unreachable from here; it's kept as a bucket future grades (or a future data source)
+/// can map onto, rather than left out of the enum.
+fn overall_severity(severities: impl Iterator<Item = Severity>) -> Severity {
+    severities.max().unwrap_or(Severity::Good)
+}
+
+/// Interprets `status`'s per-pollutant grades into health advisories and an overall
+/// worst-case severity for the station.
+pub fn interpret(status: &airkorea::AirStatus) -> AirHealth {
+    let pollutants: Vec<PollutantHealth> = status
+        .pollutants
+        .iter()
+        .filter_map(|pollutant| {
+            let severity = Severity::from_grade(pollutant.grade?)?;
+            Some(PollutantHealth {
+                name: pollutant.name.clone(),
+                advisory: severity.advisory().to_string(),
+                severity,
+            })
+        })
+        .collect();
+
+    let overall = overall_severity(pollutants.iter().map(|pollutant| pollutant.severity));
+
+    AirHealth {
+        advisory: overall.advisory().to_string(),
+        overall,
+        pollutants,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severity_orders_from_good_to_hazardous() {
+        assert!(Severity::Good < Severity::Moderate);
+        assert!(Severity::Moderate < Severity::Unhealthy);
+        assert!(Severity::Unhealthy < Severity::VeryUnhealthy);
+        assert!(Severity::VeryUnhealthy < Severity::Hazardous);
+    }
+
+    #[test]
+    fn from_grade_maps_every_real_grade() {
+        assert_eq!(Severity::from_grade(airkorea::Grade::Good), Some(Severity::Good));
+        assert_eq!(Severity::from_grade(airkorea::Grade::Normal), Some(Severity::Moderate));
+        assert_eq!(Severity::from_grade(airkorea::Grade::Bad), Some(Severity::Unhealthy));
+        assert_eq!(
+            Severity::from_grade(airkorea::Grade::VeryBad),
+            Some(Severity::VeryUnhealthy)
+        );
+    }
+
+    #[test]
+    fn from_grade_treats_strange_as_unknown() {
+        assert_eq!(Severity::from_grade(airkorea::Grade::Strange), None);
+    }
+
+    #[test]
+    fn overall_severity_is_the_worst_of_its_pollutants() {
+        let severities = vec![Severity::VeryUnhealthy, Severity::Moderate];
+        assert_eq!(
+            overall_severity(severities.into_iter()),
+            Severity::VeryUnhealthy
+        );
+    }
+
+    #[test]
+    fn overall_severity_is_good_with_no_pollutants() {
+        assert_eq!(overall_severity(std::iter::empty()), Severity::Good);
+    }
+}
@@ -0,0 +1,298 @@
+use {
+    crate::{AirPollutionResponse, Config, PipelineFailure, Request, Response},
+    failure::{Error, Fail},
+    futures::prelude::*,
+    serde::Deserializer,
+    serde_derive::Deserialize,
+    std::{
+        fs,
+        net::SocketAddr,
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+    structopt::StructOpt,
+    warp::Filter,
+};
+
+/// A Matrix application service registration, as generated by a homeserver or written
+/// by hand following the application service spec.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Registration {
+    /// Base URL the homeserver should push events to. Homeservers sometimes write this
+    /// out as an empty string when it hasn't been configured yet; treat that the same
+    /// as a missing `url` instead of producing a connector that can never talk back.
+    #[serde(default, deserialize_with = "deserialize_url")]
+    pub url: Option<String>,
+    pub hs_token: String,
+    pub as_token: String,
+}
+
+fn deserialize_url<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw: Option<String> = Option::deserialize(deserializer)?;
+    Ok(raw.filter(|url| !url.is_empty()))
+}
+
+impl Registration {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let raw = fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&raw)?)
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum AppserviceError {
+    #[fail(display = "registration has no push url configured yet")]
+    MissingUrl,
+    #[fail(display = "request carried an invalid hs_token")]
+    InvalidHsToken,
+}
+
+/// Command line parameters for running backslash-z as a standalone Matrix appservice.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "backslash-z-appservice", about = "backslash-z Matrix appservice")]
+pub struct Opt {
+    /// Address to bind the HTTP listener to.
+    #[structopt(long = "bind", default_value = "127.0.0.1:8081")]
+    pub bind: SocketAddr,
+
+    /// Path to the Matrix application service registration file.
+    #[structopt(long = "registration", parse(from_os_str))]
+    pub registration: PathBuf,
+
+    /// Path to the backslash-z config file (daummap app key, command aliases).
+    #[structopt(long = "config", parse(from_os_str))]
+    pub config: PathBuf,
+}
+
+/// Loads the registration and config files named in `opt`, then serves the transactions
+/// endpoint against the existing `Request`/`Response` pipeline until the process exits.
+pub fn run(opt: Opt) -> Result<(), Error> {
+    let registration = Registration::from_file(&opt.registration)?;
+    let config = Config::from_file(&opt.config)?;
+
+    AppService::new(registration, config).run(opt.bind);
+    Ok(())
+}
+
+/// A single room event from a homeserver transaction, trimmed down to what this crate
+/// needs to act on it.
+#[derive(Debug, Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    event_type: String,
+    room_id: Option<String>,
+    content: EventContent,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventContent {
+    #[serde(default)]
+    body: Option<String>,
+}
+
+/// The body a homeserver `PUT`s to `/_matrix/app/v1/transactions/:txn_id`.
+#[derive(Debug, Deserialize)]
+struct Transaction {
+    events: Vec<Event>,
+}
+
+/// Connects backslash-z to a Matrix homeserver as an application service, feeding every
+/// room message through the existing `Request`/`Response` pipeline unchanged.
+#[derive(Debug, Clone)]
+pub struct AppService {
+    registration: Registration,
+    config: Config,
+}
+
+impl AppService {
+    pub fn new(registration: Registration, config: Config) -> Self {
+        AppService {
+            registration,
+            config,
+        }
+    }
+
+    /// Checks an incoming transaction's `hs_token` against the registration before any
+    /// event in it is processed.
+    pub fn authenticate(&self, hs_token: &str) -> Result<(), Error> {
+        if hs_token == self.registration.hs_token {
+            Ok(())
+        } else {
+            Err(AppserviceError::InvalidHsToken.into())
+        }
+    }
+
+    /// Parses `body` through the configured `Commands` table (falling back to the
+    /// built-in defaults, same as `server`), runs it, and posts the formatted `Response`
+    /// back into `room_id`. Returns `AppserviceError::MissingUrl` instead of panicking
+    /// when the registration has no push url to call back on.
+    pub fn handle_message(
+        &self,
+        room_id: &str,
+        body: &str,
+    ) -> impl Future<Item = (), Error = Error> {
+        let homeserver_url = self.registration.url.clone();
+        let as_token = self.registration.as_token.clone();
+        let room_id = room_id.to_string();
+        let config = self.config.clone();
+
+        Request::parse(body, &config.commands)
+            .into_future()
+            .and_then(move |request| request.request(&config))
+            .and_then(move |response| {
+                homeserver_url
+                    .ok_or_else(|| AppserviceError::MissingUrl.into())
+                    .into_future()
+                    .and_then(move |url| post_response(url, as_token, room_id, response))
+            })
+    }
+
+    /// Handles one transaction's worth of room events: authenticates it against the
+    /// registration, then feeds every `m.room.message` body through `handle_message`.
+    fn handle_transaction(&self, hs_token: &str, transaction: Transaction) -> Result<(), Error> {
+        self.authenticate(hs_token)?;
+
+        for event in transaction.events {
+            if event.event_type != "m.room.message" {
+                continue;
+            }
+
+            if let (Some(room_id), Some(body)) = (event.room_id, event.content.body) {
+                tokio::spawn(self.handle_message(&room_id, &body).map_err(|err| {
+                    tracing::warn!("failed to handle room message: {}", err);
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the `PUT /_matrix/app/v1/transactions/:txn_id` route a homeserver polls or
+    /// pushes transactions to, wired up to the existing request pipeline.
+    fn routes(
+        self: Arc<Self>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("_matrix" / "app" / "v1" / "transactions" / String)
+            .and(warp::put2())
+            .and(warp::header::<String>("authorization"))
+            .and(warp::body::json())
+            .and_then(move |_txn_id: String, authorization: String, transaction: Transaction| {
+                let hs_token = authorization.trim_start_matches("Bearer ").to_string();
+
+                self.handle_transaction(&hs_token, transaction)
+                    .map(|()| warp::reply::json(&serde_json::json!({})))
+                    .map_err(|err| warp::reject::custom(PipelineFailure(err)))
+            })
+    }
+
+    /// Starts polling/receiving room events from the homeserver by serving the
+    /// transactions endpoint at `bind` until the process exits.
+    pub fn run(self, bind: SocketAddr) {
+        warp::serve(Arc::new(self).routes()).run(bind);
+    }
+}
+
+fn post_response(
+    homeserver_url: String,
+    as_token: String,
+    room_id: String,
+    response: Response,
+) -> impl Future<Item = (), Error = Error> {
+    let client = reqwest::r#async::Client::new();
+    let url = format!(
+        "{}/_matrix/client/r0/rooms/{}/send/m.room.message",
+        homeserver_url.trim_end_matches('/'),
+        room_id
+    );
+
+    client
+        .post(&url)
+        .bearer_auth(as_token)
+        .json(&serde_json::json!({
+            "msgtype": "m.text",
+            "body": format_response(&response),
+        }))
+        .send()
+        .map(|_| ())
+        .map_err(Error::from)
+}
+
+fn format_response(response: &Response) -> String {
+    match response {
+        Response::Dictionary(search) => format_dictionary(search),
+        Response::AirPollution(air) => format_air_pollution(air),
+        Response::AirHealth(health) => format_air_health(health),
+        Response::HowTo(answer) => format_howto(answer),
+    }
+}
+
+fn format_dictionary(search: &daumdic::Search) -> String {
+    if search.means.is_empty() {
+        format!("no dictionary results for '{}'", search.word)
+    } else {
+        let senses = search
+            .means
+            .iter()
+            .enumerate()
+            .map(|(i, mean)| format!("{}. {}", i + 1, mean.means.join("; ")))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("{}\n{}", search.word, senses)
+    }
+}
+
+fn format_howto(answer: &howto::Answer) -> String {
+    answer.answer.clone()
+}
+
+fn format_air_pollution(air: &AirPollutionResponse) -> String {
+    let readings = air
+        .status
+        .pollutants
+        .iter()
+        .map(|pollutant| format!("{}: {:?}", pollutant.name, pollutant.grade))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "{} - {}\n{}",
+        air.status.station_address, readings, air.health.advisory
+    )
+}
+
+fn format_air_health(health: &crate::airhealth::AirHealth) -> String {
+    health.advisory.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Wrapper {
+        #[serde(default, deserialize_with = "deserialize_url")]
+        url: Option<String>,
+    }
+
+    #[test]
+    fn empty_url_deserializes_to_none() {
+        let wrapper: Wrapper = serde_json::from_str(r#"{"url": ""}"#).unwrap();
+        assert_eq!(wrapper.url, None);
+    }
+
+    #[test]
+    fn missing_url_deserializes_to_none() {
+        let wrapper: Wrapper = serde_json::from_str(r#"{}"#).unwrap();
+        assert_eq!(wrapper.url, None);
+    }
+
+    #[test]
+    fn non_empty_url_is_kept() {
+        let wrapper: Wrapper =
+            serde_json::from_str(r#"{"url": "https://example.com"}"#).unwrap();
+        assert_eq!(wrapper.url, Some("https://example.com".to_string()));
+    }
+}
@@ -6,12 +6,64 @@ use {
     lazy_static::lazy_static,
     regex::Regex,
     serde_derive::{Deserialize, Serialize},
-    std::str::FromStr,
+    std::{collections::HashMap, fs, path::Path, str::FromStr},
 };
 
+pub mod airhealth;
+pub mod appservice;
+pub mod searcher;
+pub mod server;
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
     daummap_app_key: String,
+    #[serde(default)]
+    pub(crate) commands: Commands,
+}
+
+impl Config {
+    /// Loads a `Config` from a YAML file, the same format `appservice::Registration`
+    /// uses for its registration file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let raw = fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&raw)?)
+    }
+}
+
+/// The kind of canonical command a user-defined trigger token expands to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandKind {
+    Dictionary,
+    AirPollution,
+    AirHealth,
+    HowTo,
+}
+
+/// What a single configured trigger token expands into.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CommandDef {
+    pub kind: CommandKind,
+    /// Fixed airkorea command (e.g. `"pm"`) to use for `AirPollution` aliases. Ignored
+    /// for `Dictionary` and `HowTo`.
+    #[serde(default)]
+    pub argument: Option<String>,
+}
+
+/// A table of operator-defined trigger tokens (including non-English aliases) mapped to
+/// the command they should parse as, consulted by `Request::parse` before falling back
+/// to the built-in defaults.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Commands(HashMap<String, CommandDef>);
+
+impl Commands {
+    pub fn insert(&mut self, trigger: impl Into<String>, def: CommandDef) {
+        self.0.insert(trigger.into(), def);
+    }
+
+    fn get(&self, trigger: &str) -> Option<&CommandDef> {
+        self.0.get(trigger)
+    }
 }
 
 #[derive(Debug, Fail, PartialEq, Eq)]
@@ -24,12 +76,49 @@ pub enum RequestError {
     InvalidAirkoreaCommand(String),
     #[fail(display = "answer is not found for {}", _0)]
     HowtoNotFound(String),
+    #[fail(display = "query was cancelled")]
+    Cancelled,
 }
 
-#[derive(Debug, Clone)]
+/// Wraps the `Request`/`Response` pipeline's `failure::Error` so a transport (`server`,
+/// `appservice`) can hand it to `warp::reject::custom`, which on the warp 0.1 this crate
+/// pins for futures-0.1 compatibility rejects any `failure::Fail`, not a
+/// locally-implemented marker trait. Shared by both transports so they don't each carry
+/// their own copy of the same wrapper.
+#[derive(Debug)]
+pub struct PipelineFailure(pub Error);
+
+impl std::fmt::Display for PipelineFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Fail for PipelineFailure {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.0.as_fail().cause()
+    }
+
+    fn backtrace(&self) -> Option<&failure::Backtrace> {
+        Some(self.0.backtrace())
+    }
+}
+
+/// The raw `airkorea::AirStatus` alongside its interpreted health advisory, so consumers
+/// get both the numbers and a summary they can act on without knowing what a µg/m³
+/// figure means.
+#[derive(Debug, Clone, Serialize)]
+pub struct AirPollutionResponse {
+    pub status: airkorea::AirStatus,
+    pub health: airhealth::AirHealth,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "payload")]
 pub enum Response {
     Dictionary(daumdic::Search),
-    AirPollution(airkorea::AirStatus),
+    AirPollution(AirPollutionResponse),
+    AirHealth(airhealth::AirHealth),
     HowTo(howto::Answer),
 }
 
@@ -37,6 +126,7 @@ pub enum Response {
 pub enum Request {
     Dictionary(String),
     AirPollution(String, String),
+    AirHealth(String),
     HowTo(String),
 }
 
@@ -48,6 +138,7 @@ impl FromStr for Request {
             static ref REGEX_DIC: Regex = Regex::new(r"^[dD](?:ic)? (.+)$").unwrap();
             static ref REGEX_AIR: Regex =
                 Regex::new(r"^(air|pm|pm10|pm25|o3|so2|no2|co|so2) (.+)$").unwrap();
+            static ref REGEX_AIRHEALTH: Regex = Regex::new(r"^airhealth (.+)$").unwrap();
             static ref REGEX_HOWTO: Regex = Regex::new(r"^[hH](?:owto)? (.+)$").unwrap();
         }
 
@@ -66,6 +157,12 @@ impl FromStr for Request {
                     })
                     .map(|(s1, s2)| Request::AirPollution(s1, s2))
             })
+            .or_else(|| {
+                REGEX_AIRHEALTH
+                    .captures(message)
+                    .map(|c| c.get(1).unwrap().as_str().to_owned())
+                    .map(Request::AirHealth)
+            })
             .or_else(|| {
                 REGEX_HOWTO
                     .captures(message)
@@ -77,17 +174,43 @@ impl FromStr for Request {
 }
 
 impl Request {
+    /// Parses `message` against the operator-configured `Commands` table first, falling
+    /// back to the built-in defaults (`FromStr`) when no trigger token matches.
+    pub fn parse(message: &str, commands: &Commands) -> Result<Self, Error> {
+        let mut parts = message.splitn(2, ' ');
+        let token = parts.next().unwrap_or("");
+        let rest = parts.next();
+
+        if let (Some(def), Some(rest)) = (commands.get(token), rest) {
+            return Ok(match def.kind {
+                CommandKind::Dictionary => Request::Dictionary(rest.to_string()),
+                CommandKind::HowTo => Request::HowTo(rest.to_string()),
+                CommandKind::AirPollution => Request::AirPollution(
+                    def.argument.clone().unwrap_or_else(|| token.to_string()),
+                    rest.to_string(),
+                ),
+                CommandKind::AirHealth => Request::AirHealth(rest.to_string()),
+            });
+        }
+
+        message.parse()
+    }
+
     pub fn request(self, config: &Config) -> impl Future<Item = Response, Error = Error> {
         use futures::future::Either;
 
         match self {
-            Request::Dictionary(query) => Either::A(search_dic(&query)),
-            Request::AirPollution(command, query) => Either::B(Either::A(search_air(
-                &command,
+            Request::Dictionary(query) => {
+                Either::A(Either::A(Either::A(search_dic(&query))))
+            }
+            Request::AirPollution(command, query) => Either::A(Either::A(Either::B(
+                search_air(&command, &query, &config.daummap_app_key),
+            ))),
+            Request::AirHealth(query) => Either::A(Either::B(search_airhealth(
                 &query,
                 &config.daummap_app_key,
             ))),
-            Request::HowTo(query) => Either::B(Either::B(search_howto(&query))),
+            Request::HowTo(query) => Either::B(search_howto(&query)),
         }
     }
 }
@@ -115,12 +238,10 @@ fn search_dic(query: &str) -> impl Future<Item = Response, Error = Error> {
     daumdic::search(query).map(Response::Dictionary)
 }
 
-fn search_air(
-    command: &str,
+fn locate_air_status(
     query: &str,
     app_key: &str,
-) -> impl Future<Item = Response, Error = Error> {
-    let command = command.to_string();
+) -> impl Future<Item = airkorea::AirStatus, Error = Error> {
     let query = query.to_string();
     let app_key = app_key.to_string();
 
@@ -146,30 +267,46 @@ fn search_air(
             }
         })
         .and_then(|(longitude, latitude)| airkorea::search(longitude, latitude))
-        .and_then(move |status| {
-            let station_address = status.station_address.clone();
-            let pollutants = match command.as_ref() {
-                "air" => status.pollutants,
-                "pm" => status
-                    .into_iter()
-                    .filter(|p| p.name.contains("PM"))
-                    .collect(),
-                command => status
-                    .into_iter()
-                    .filter(|p| p.name.to_lowercase().contains(&command))
-                    .collect(),
+}
+
+fn search_air(
+    command: &str,
+    query: &str,
+    app_key: &str,
+) -> impl Future<Item = Response, Error = Error> {
+    let command = command.to_string();
+
+    locate_air_status(query, app_key).and_then(move |status| {
+        let station_address = status.station_address.clone();
+        let pollutants = match command.as_ref() {
+            "air" => status.pollutants,
+            "pm" => status
+                .into_iter()
+                .filter(|p| p.name.contains("PM"))
+                .collect(),
+            command => status
+                .into_iter()
+                .filter(|p| p.name.to_lowercase().contains(&command))
+                .collect(),
+        };
+
+        if pollutants.is_empty() {
+            Err(RequestError::InvalidAirkoreaCommand(command).into())
+        } else {
+            let status = airkorea::AirStatus {
+                station_address,
+                pollutants,
             };
+            let health = airhealth::interpret(&status);
+            Ok(Response::AirPollution(AirPollutionResponse { status, health }))
+        }
+    })
+}
 
-            if pollutants.is_empty() {
-                Err(RequestError::InvalidAirkoreaCommand(command).into())
-            } else {
-                Ok(airkorea::AirStatus {
-                    station_address,
-                    pollutants,
-                })
-            }
-        })
-        .map(Response::AirPollution)
+fn search_airhealth(query: &str, app_key: &str) -> impl Future<Item = Response, Error = Error> {
+    locate_air_status(query, app_key)
+        .map(|status| airhealth::interpret(&status))
+        .map(Response::AirHealth)
 }
 
 fn search_howto(query: &str) -> impl Future<Item = Response, Error = Error> {
@@ -180,3 +317,61 @@ fn search_howto(query: &str) -> impl Future<Item = Response, Error = Error> {
         .and_then(|(answer, _)| answer.ok_or_else(|| RequestError::HowtoNotFound(query).into()))
         .map(Response::HowTo)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_resolves_configured_alias_before_falling_back() {
+        let mut commands = Commands::default();
+        commands.insert(
+            "사전",
+            CommandDef {
+                kind: CommandKind::Dictionary,
+                argument: None,
+            },
+        );
+        commands.insert(
+            "미세먼지",
+            CommandDef {
+                kind: CommandKind::AirPollution,
+                argument: Some("pm".to_string()),
+            },
+        );
+
+        match Request::parse("사전 안녕", &commands).unwrap() {
+            Request::Dictionary(query) => assert_eq!(query, "안녕"),
+            other => panic!("expected Request::Dictionary, got {:?}", other),
+        }
+
+        match Request::parse("미세먼지 서울", &commands).unwrap() {
+            Request::AirPollution(command, query) => {
+                assert_eq!(command, "pm");
+                assert_eq!(query, "서울");
+            }
+            other => panic!("expected Request::AirPollution, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_falls_back_to_built_in_defaults_when_no_alias_matches() {
+        let commands = Commands::default();
+
+        match Request::parse("d 안녕", &commands).unwrap() {
+            Request::Dictionary(query) => assert_eq!(query, "안녕"),
+            other => panic!("expected Request::Dictionary, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_reports_cannot_parse_request_when_nothing_matches() {
+        let commands = Commands::default();
+
+        let err = Request::parse("not a command", &commands).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RequestError>(),
+            Some(&RequestError::CannotParseRequest("not a command".to_string()))
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use {
+    backslash_z::{appservice, server, Config},
+    structopt::StructOpt,
+};
+
+/// Which of the two transports to run backslash-z's `Request`/`Response` pipeline over.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "backslash-z", about = "run backslash-z as a standalone service")]
+enum Opt {
+    /// Run the standalone HTTP query server.
+    Server(server::Opt),
+    /// Run as a Matrix application service.
+    Appservice(appservice::Opt),
+}
+
+fn main() {
+    match Opt::from_args() {
+        Opt::Server(opt) => {
+            let config = Config::from_file(&opt.config)
+                .unwrap_or_else(|err| panic!("failed to load config: {}", err));
+            server::run(opt, config);
+        }
+        Opt::Appservice(opt) => {
+            if let Err(err) = appservice::run(opt) {
+                eprintln!("failed to run appservice: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use {
+    crate::{AirPollutionResponse, Config, Request, RequestError, Response},
+    failure::Error,
+    futures::{future::Either, prelude::*, sync::oneshot},
+    std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    },
+};
+
+/// Identifies a query started through `Searcher::search`, for later cancellation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryId(usize);
+
+type CancelTokens = Arc<Mutex<HashMap<QueryId, oneshot::Sender<()>>>>;
+
+/// Runs `Request`s as cancellable streams instead of a single blocking future, so a
+/// front-end can show partial progress and abort a hung lookup. Each `Searcher` owns its
+/// own registry of in-flight queries and its own id counter, so independent instances
+/// can't observe or cancel each other's work.
+#[derive(Clone, Default)]
+pub struct Searcher {
+    next_id: Arc<AtomicUsize>,
+    tokens: CancelTokens,
+}
+
+impl std::fmt::Debug for Searcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Searcher").finish()
+    }
+}
+
+impl Searcher {
+    pub fn new() -> Self {
+        Searcher::default()
+    }
+
+    fn next_query_id(&self) -> QueryId {
+        QueryId(self.next_id.fetch_add(1, Ordering::SeqCst))
+    }
+
+    /// Starts `request` against `config` and returns its `QueryId` alongside a stream of
+    /// the partial `Response`s it produces as they arrive (e.g. one `AirPollution` item
+    /// per pollutant). The stream ends with `RequestError::Cancelled` if `cancel` is
+    /// called for this id before the query finishes, and frees its registry slot if it's
+    /// dropped before either of those happens (e.g. a caller stops polling early).
+    pub fn search(
+        &self,
+        request: Request,
+        config: &Config,
+    ) -> (QueryId, impl Stream<Item = Response, Error = Error>) {
+        self.track(request.request(config))
+    }
+
+    /// Registers `future` as an in-flight query and races it against its cancellation
+    /// channel, broken out from `search` so the cancellation/race mechanics can be
+    /// exercised with a synthetic future in tests.
+    fn track<F>(&self, future: F) -> (QueryId, impl Stream<Item = Response, Error = Error>)
+    where
+        F: Future<Item = Response, Error = Error>,
+    {
+        let id = self.next_query_id();
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+
+        self.tokens.lock().unwrap().insert(id, cancel_tx);
+
+        let tokens = self.tokens.clone();
+        let stream = future
+            .select2(cancel_rx)
+            .then(move |result| {
+                tokens.lock().unwrap().remove(&id);
+
+                match result {
+                    Ok(Either::A((response, _))) => Ok(partials(response)),
+                    Ok(Either::B((_, _))) => Err(RequestError::Cancelled.into()),
+                    Err(Either::A((err, _))) => Err(err),
+                    Err(Either::B((_, _))) => Err(RequestError::Cancelled.into()),
+                }
+            })
+            .map(|items| futures::stream::iter_ok::<_, Error>(items))
+            .flatten_stream();
+
+        (
+            id,
+            TrackedStream {
+                inner: stream,
+                tokens: self.tokens.clone(),
+                id,
+            },
+        )
+    }
+
+    /// Stops the query identified by `id` if it is still in flight.
+    pub fn cancel(&self, id: QueryId) {
+        if let Some(cancel_tx) = self.tokens.lock().unwrap().remove(&id) {
+            let _ = cancel_tx.send(());
+        }
+    }
+}
+
+/// Splits a finished `Response` into the partial items a caller should see as they
+/// arrive: `AirPollution` fans out one item per pollutant and `Dictionary` fans out one
+/// item per sense, mirroring how each is assembled in the first place (`airkorea::AirStatus`
+/// and `daumdic::Search` both already group their results this way). The remaining
+/// variants carry nothing to split, so they stay a single item.
+fn partials(response: Response) -> Vec<Response> {
+    match response {
+        Response::AirPollution(AirPollutionResponse { status, .. }) => {
+            let airkorea::AirStatus {
+                station_address,
+                pollutants,
+            } = status;
+
+            pollutants
+                .into_iter()
+                .map(move |pollutant| {
+                    let status = airkorea::AirStatus {
+                        station_address: station_address.clone(),
+                        pollutants: vec![pollutant],
+                    };
+                    let health = crate::airhealth::interpret(&status);
+                    Response::AirPollution(AirPollutionResponse { status, health })
+                })
+                .collect()
+        }
+        Response::Dictionary(daumdic::Search { word, means }) => means
+            .into_iter()
+            .map(|mean| {
+                Response::Dictionary(daumdic::Search {
+                    word: word.clone(),
+                    means: vec![mean],
+                })
+            })
+            .collect(),
+        other => vec![other],
+    }
+}
+
+/// Wraps the stream `Searcher::track` hands back so its `tokens` slot is freed even if
+/// the caller drops the stream without ever calling `cancel` or polling it to
+/// completion (e.g. a client disconnects mid-query). The happy path already removes the
+/// entry as soon as the raced future resolves; this covers the early-abandonment case
+/// that path can't see.
+struct TrackedStream<S> {
+    inner: S,
+    tokens: CancelTokens,
+    id: QueryId,
+}
+
+impl<S: Stream> Stream for TrackedStream<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> futures::Poll<Option<Self::Item>, Self::Error> {
+        self.inner.poll()
+    }
+}
+
+impl<S> Drop for TrackedStream<S> {
+    fn drop(&mut self) {
+        self.tokens.lock().unwrap().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+
+    #[test]
+    fn cancel_short_circuits_the_stream_with_cancelled() {
+        let searcher = Searcher::new();
+        let (id, stream) = searcher.track(future::empty());
+
+        searcher.cancel(id);
+
+        match stream.collect().wait() {
+            Err(err) => assert_eq!(
+                err.downcast_ref::<RequestError>(),
+                Some(&RequestError::Cancelled)
+            ),
+            Ok(items) => panic!("expected cancellation error, got {:?}", items),
+        }
+    }
+
+    #[test]
+    fn cancel_of_an_unknown_query_id_is_a_noop() {
+        let searcher = Searcher::new();
+
+        // Never registered with this searcher; must not panic.
+        searcher.cancel(QueryId(12345));
+    }
+
+    #[test]
+    fn independent_searchers_do_not_share_cancel_tokens() {
+        let a = Searcher::new();
+        let b = Searcher::new();
+
+        let (id, stream) = a.track(future::empty());
+        b.cancel(id);
+
+        // `b` has no record of `id`, so `a`'s query must still be in flight and
+        // uncancelled; cancelling through `a` now should still short-circuit it.
+        a.cancel(id);
+        match stream.collect().wait() {
+            Err(err) => assert_eq!(
+                err.downcast_ref::<RequestError>(),
+                Some(&RequestError::Cancelled)
+            ),
+            Ok(items) => panic!("expected cancellation error, got {:?}", items),
+        }
+    }
+
+    #[test]
+    fn dropping_the_stream_without_consuming_it_frees_the_registry_slot() {
+        let searcher = Searcher::new();
+        let (id, stream) = searcher.track(future::empty());
+
+        assert!(searcher.tokens.lock().unwrap().contains_key(&id));
+
+        drop(stream);
+
+        assert!(!searcher.tokens.lock().unwrap().contains_key(&id));
+    }
+
+    #[test]
+    fn partials_fans_out_one_item_per_pollutant() {
+        let status = airkorea::AirStatus {
+            station_address: "Seoul".to_string(),
+            pollutants: vec![
+                airkorea::Pollutant {
+                    name: "PM10".to_string(),
+                    grade: Some(airkorea::Grade::Good),
+                },
+                airkorea::Pollutant {
+                    name: "PM25".to_string(),
+                    grade: Some(airkorea::Grade::Normal),
+                },
+            ],
+        };
+        let health = crate::airhealth::interpret(&status);
+        let response = Response::AirPollution(AirPollutionResponse { status, health });
+
+        let items = partials(response);
+        assert_eq!(items.len(), 2);
+        for item in items {
+            match item {
+                Response::AirPollution(AirPollutionResponse { status, .. }) => {
+                    assert_eq!(status.pollutants.len(), 1);
+                }
+                other => panic!("expected Response::AirPollution, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn partials_fans_out_one_item_per_dictionary_sense() {
+        let search = daumdic::Search {
+            word: "hello".to_string(),
+            means: vec![
+                daumdic::Mean {
+                    means: vec!["an expression of greeting".to_string()],
+                },
+                daumdic::Mean {
+                    means: vec!["to say or shout hello".to_string()],
+                },
+            ],
+        };
+        let response = Response::Dictionary(search);
+
+        let items = partials(response);
+        assert_eq!(items.len(), 2);
+        for item in items {
+            match item {
+                Response::Dictionary(search) => assert_eq!(search.means.len(), 1),
+                other => panic!("expected Response::Dictionary, got {:?}", other),
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use {
+    crate::{Config, PipelineFailure, Request, RequestError, Response},
+    failure::{Error, Fail},
+    futures::{future, prelude::*},
+    serde_derive::Deserialize,
+    std::{
+        net::SocketAddr,
+        path::PathBuf,
+        str::FromStr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    },
+    structopt::StructOpt,
+    warp::{http::StatusCode, Filter},
+};
+
+/// Command line parameters for running backslash-z as a standalone HTTP service.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "backslash-z", about = "backslash-z HTTP server")]
+pub struct Opt {
+    /// Address to bind the HTTP listener to.
+    #[structopt(long = "bind", default_value = "127.0.0.1:8080")]
+    pub bind: SocketAddr,
+
+    /// Path to the backslash-z config file (daummap app key, command aliases).
+    #[structopt(long = "config", parse(from_os_str))]
+    pub config: PathBuf,
+
+    /// Logging verbosity: trace, debug, info, warn or error.
+    #[structopt(long = "log-level", default_value = "info")]
+    pub log_level: LogLevel,
+
+    /// Optional file to write the process id to after startup.
+    #[structopt(long = "pid-file", parse(from_os_str))]
+    pub pid_file: Option<PathBuf>,
+
+    /// Reject incoming requests once this many are already in flight.
+    #[structopt(long = "max-concurrent-requests", default_value = "32")]
+    pub max_concurrent_requests: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LogLevel(pub tracing::Level);
+
+impl FromStr for LogLevel {
+    type Err = Error;
+
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level {
+            "trace" => Ok(LogLevel(tracing::Level::TRACE)),
+            "debug" => Ok(LogLevel(tracing::Level::DEBUG)),
+            "info" => Ok(LogLevel(tracing::Level::INFO)),
+            "warn" => Ok(LogLevel(tracing::Level::WARN)),
+            "error" => Ok(LogLevel(tracing::Level::ERROR)),
+            level => Err(ServerError::InvalidLogLevel(level.to_string()).into()),
+        }
+    }
+}
+
+#[derive(Debug, Fail, PartialEq, Eq)]
+pub enum ServerError {
+    #[fail(display = "{} is not a valid log level", _0)]
+    InvalidLogLevel(String),
+    #[fail(display = "too many concurrent requests")]
+    TooManyRequests,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryBody {
+    message: String,
+}
+
+fn write_pid_file(path: &std::path::Path) -> Result<(), Error> {
+    std::fs::write(path, std::process::id().to_string())?;
+    Ok(())
+}
+
+/// Maps a rejection back to a status code and JSON body callers can act on, instead of
+/// warp's default undifferentiated 500. Rejections this doesn't recognize are passed
+/// through unchanged.
+fn recover(rejection: warp::Rejection) -> Result<impl warp::Reply, warp::Rejection> {
+    let (status, message) = if let Some(err) = rejection.find_cause::<ServerError>() {
+        let status = match err {
+            ServerError::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            ServerError::InvalidLogLevel(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, err.to_string())
+    } else if let Some(failure) = rejection.find_cause::<PipelineFailure>() {
+        let status = match failure.0.downcast_ref::<RequestError>() {
+            Some(RequestError::CannotParseRequest(_))
+            | Some(RequestError::InvalidAirkoreaCommand(_)) => StatusCode::BAD_REQUEST,
+            Some(RequestError::AddressNotFound(_)) | Some(RequestError::HowtoNotFound(_)) => {
+                StatusCode::NOT_FOUND
+            }
+            Some(RequestError::Cancelled) | None => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, failure.0.to_string())
+    } else {
+        return Err(rejection);
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({ "error": message })),
+        status,
+    ))
+}
+
+/// Runs `POST /query` against the existing `Request`/`Response` pipeline until the process exits.
+pub fn run(opt: Opt, config: Config) {
+    tracing_subscriber::fmt()
+        .with_max_level(opt.log_level.0)
+        .init();
+
+    if let Some(pid_file) = &opt.pid_file {
+        if let Err(err) = write_pid_file(pid_file) {
+            tracing::warn!("failed to write pid file: {}", err);
+        }
+    }
+
+    let config = Arc::new(config);
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_concurrent_requests = opt.max_concurrent_requests;
+
+    let query = warp::path("query")
+        .and(warp::post2())
+        .and(warp::body::json())
+        .and_then(move |body: QueryBody| {
+            let config = config.clone();
+            let in_flight = in_flight.clone();
+
+            if in_flight.fetch_add(1, Ordering::SeqCst) >= max_concurrent_requests {
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                return future::Either::A(future::err(warp::reject::custom(
+                    ServerError::TooManyRequests,
+                )));
+            }
+
+            let in_flight_done = in_flight.clone();
+            let response = Request::parse(&body.message, &config.commands)
+                .into_future()
+                .and_then(move |request| request.request(&config))
+                .then(move |result| {
+                    in_flight_done.fetch_sub(1, Ordering::SeqCst);
+                    result
+                })
+                .map(|response| warp::reply::json(&response))
+                .map_err(|err| warp::reject::custom(PipelineFailure(err)));
+
+            future::Either::B(response)
+        });
+
+    warp::serve(query.recover(recover)).run(opt.bind);
+}